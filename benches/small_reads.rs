@@ -0,0 +1,33 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::io;
+use std::io::Read;
+
+use arraydeque::io::BufReader;
+
+use test::Bencher;
+
+/// A reader that endlessly yields zero bytes without ever hitting EOF.
+struct Zeroes;
+
+impl Read for Zeroes {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        for b in buf.iter_mut() {
+            *b = 0;
+        }
+        Ok(buf.len())
+    }
+}
+
+#[bench]
+fn many_tiny_reads(b: &mut Bencher) {
+    let mut reader = BufReader::<_, [u8; 4096]>::new(Zeroes);
+    let mut scratch = [0u8; 8];
+    b.iter(|| {
+        for len in 1..=8 {
+            reader.read(&mut scratch[..len]).unwrap();
+        }
+    });
+}
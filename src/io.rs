@@ -1,14 +1,72 @@
 //! Buffered I/O based on `ArrayDeque`.
+//!
+//! The nightly [`Read::read_buf`] fast path is opt-in via the `read_buf`
+//! cargo feature, which must be declared in `Cargo.toml`:
+//!
+//! ```toml
+//! [features]
+//! read_buf = []
+//! ```
+//!
+//! The declaration also registers the cfg with `rustc`/`clippy`; without it
+//! the `unexpected_cfgs` lint fires under `-D warnings`, so we additionally
+//! allow it here for builds of this module in isolation.
+#![allow(unexpected_cfgs)]
 
 use std::io;
 use std::io::BufRead;
+use std::io::IoSlice;
+#[cfg(not(feature = "read_buf"))]
 use std::io::IoSliceMut;
 use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
 
 use crate::Array;
 use crate::ArrayDeque;
 use crate::behavior::Saturating;
 
+/// Return type of [`ReaderPolicy::before_read`] telling the reader whether to
+/// issue a refill before handing out buffered data.
+pub struct DoRead(pub bool);
+
+/// Strategy controlling when a [`BufReader`] refills its internal deque.
+///
+/// Implementors may force or suppress a read at the top of `fill_buf` and react
+/// to bytes leaving the buffer, letting parsers ask for more lookahead.
+pub trait ReaderPolicy {
+    /// Consulted at the top of `fill_buf`; return `DoRead(true)` to pull more
+    /// bytes from the inner reader even when data is already buffered.
+    fn before_read<A>(&mut self, buf: &ArrayDeque<A, Saturating>) -> DoRead
+            where A : Array<Item=u8> {
+        DoRead(buf.is_empty())
+    }
+
+    /// Called after `amt` bytes have been consumed from the head of `buf`.
+    fn after_consume<A>(&mut self, _buf: &ArrayDeque<A, Saturating>, _amt: usize)
+            where A : Array<Item=u8> {}
+}
+
+/// The default policy: refill the deque only when it is empty.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdPolicy;
+
+impl ReaderPolicy for StdPolicy {}
+
+/// Refills the deque whenever fewer than the given number of bytes remain
+/// buffered. Each refill is a single read of the inner reader, so the deque
+/// may still hold fewer bytes than the threshold afterwards.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MinBuffered(pub usize);
+
+impl ReaderPolicy for MinBuffered {
+    fn before_read<A>(&mut self, buf: &ArrayDeque<A, Saturating>) -> DoRead
+            where A : Array<Item=u8> {
+        DoRead(buf.len() < self.0)
+    }
+}
+
 struct Guard<'a, A> where A : Array {
     buf: &'a mut ArrayDeque<A, Saturating>,
     len: usize,
@@ -23,19 +81,22 @@ impl<A> Drop for Guard<'_, A> where A : Array {
 }
 
 /// `std::io::BufReader` replacement driven by `ArrayDeque`.
-pub struct BufReader<R, A> where A : Array<Item=u8> {
+pub struct BufReader<R, A, P = StdPolicy> where A : Array<Item=u8> {
     inner: R,
     buf: ArrayDeque<A, Saturating>,
+    policy: P,
+    read: u64,
+    progress: Option<Box<dyn FnMut(u64) + Send>>,
 }
 
-impl<R: Read, A : Array<Item=u8>> BufReader<R, A> {
+impl<R: Read, A : Array<Item=u8>> BufReader<R, A, StdPolicy> {
     /// Creates a new BufReader<R, A>
-    pub fn new(inner: R) -> BufReader<R, A> {
-        BufReader { inner, buf: ArrayDeque::<A, Saturating>::new() }
+    pub fn new(inner: R) -> BufReader<R, A, StdPolicy> {
+        BufReader { inner, buf: ArrayDeque::<A, Saturating>::new(), policy: StdPolicy, read: 0, progress: None }
     }
 }
 
-impl<R, A : Array<Item=u8>> BufReader<R, A> {
+impl<R, A : Array<Item=u8>, P> BufReader<R, A, P> {
     /// Gets a reference to the underlying reader.
     pub fn get_ref(&self) -> &R {
         &self.inner
@@ -60,9 +121,41 @@ impl<R, A : Array<Item=u8>> BufReader<R, A> {
     pub fn buffer_mut(&mut self) -> &mut ArrayDeque::<A, Saturating> {
         &mut self.buf
     }
+
+    /// Returns a reference to the current [`ReaderPolicy`].
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// Returns a mutable reference to the current [`ReaderPolicy`].
+    pub fn policy_mut(&mut self) -> &mut P {
+        &mut self.policy
+    }
+
+    /// Replaces the reader policy, returning a `BufReader` parameterized over
+    /// the new policy type while preserving the inner reader and buffer.
+    pub fn set_policy<Q>(self, policy: Q) -> BufReader<R, A, Q> {
+        BufReader { inner: self.inner, buf: self.buf, policy, read: self.read, progress: self.progress }
+    }
+
+    /// Returns the total number of bytes consumed from this reader so far,
+    /// i.e. bytes pulled from the inner reader less those still buffered.
+    pub fn bytes_read(&self) -> u64 {
+        self.read - self.buf.len() as u64
+    }
+
+    /// Registers a callback invoked from [`try_fill_buf`](Self::try_fill_buf)
+    /// after every refill with the cumulative number of bytes pulled from the
+    /// inner reader.
+    ///
+    /// Instrumenting at the buffering layer lets progress bars over large files
+    /// avoid wrapping the inner reader and double counting.
+    pub fn set_progress(&mut self, progress: impl FnMut(u64) + Send + 'static) {
+        self.progress = Some(Box::new(progress));
+    }
 }
 
-impl<R: Read, A : Array<Item=u8>> BufReader<R, A> {
+impl<R: Read, A : Array<Item=u8>, P: ReaderPolicy> BufReader<R, A, P> {
     /// Tries to fill the internal deque from the internal reader
     ///
     /// Since this function tries to read from the internal reader if the deque
@@ -90,35 +183,235 @@ impl<R: Read, A : Array<Item=u8>> BufReader<R, A> {
         }
 
         let bufs = g.buf.as_mut_slices();
-        let (advance1, advance2) = match (bufs.0.len(), bufs.1.len()) {
-            (len, _) if empty_pos < len =>
-                (empty_pos, 0),
-            (len1, len2) if empty_pos >= len1 && empty_pos - len1 < len2 =>
-                (len1, empty_pos - len1),
-            x => x,
+
+        // Stable path: fill both free halves of the ring in one vectored read.
+        //
+        // HAZARD: `set_len(capacity())` above grows the deque over the backing
+        // array's uninitialized tail, and `as_mut_slices()` then exposes that
+        // region as `&mut [u8]`, which asserts it is initialized even though it
+        // may not be. `read_vectored` only writes, so no uninitialized byte is
+        // ever read, but forming the reference is unsound. The `read_buf` path
+        // below goes through the same `as_mut_slices()` call, so it forms this
+        // reference too.
+        #[cfg(not(feature = "read_buf"))]
+        let bytes = {
+            let (advance1, advance2) = match (bufs.0.len(), bufs.1.len()) {
+                (len, _) if empty_pos < len =>
+                    (empty_pos, 0),
+                (len1, len2) if empty_pos >= len1 && empty_pos - len1 < len2 =>
+                    (len1, empty_pos - len1),
+                x => x,
+            };
+
+            let mut bufs = [IoSliceMut::new(&mut bufs.0[advance1..]), IoSliceMut::new(&mut bufs.1[advance2..])];
+            self.inner.read_vectored(&mut bufs)?
+        };
+
+        // Nightly `read_buf` path (opt-in via the `read_buf` feature): fill the
+        // first contiguous free region through a `BorrowedBuf`, so the inner
+        // reader sees that region as uninitialized. The `&mut [u8]` formed by
+        // `as_mut_slices()` above still exists here. A subsequent call picks up
+        // the wrapped-around half once the head advances.
+        #[cfg(feature = "read_buf")]
+        let bytes = {
+            let free = match (bufs.0.len(), bufs.1.len()) {
+                (len, _) if empty_pos < len =>
+                    &mut bufs.0[empty_pos..],
+                (len1, len2) if empty_pos - len1 < len2 =>
+                    &mut bufs.1[empty_pos - len1..],
+                _ =>
+                    &mut bufs.1[..0],
+            };
+
+            // SAFETY: weakening `&mut [u8]` to `&mut [MaybeUninit<u8>]` is always
+            // sound — it only drops the initialization assertion that
+            // `as_mut_slices` makes over the still-uninitialized array tail.
+            let free: &mut [std::mem::MaybeUninit<u8>] = unsafe {
+                std::mem::transmute::<&mut [u8], &mut [std::mem::MaybeUninit<u8>]>(free)
+            };
+            let mut borrowed = io::BorrowedBuf::from(free);
+            let mut cursor = borrowed.unfilled();
+            self.inner.read_buf(cursor.reborrow())?;
+            cursor.written()
         };
 
-        let mut bufs = [IoSliceMut::new(&mut bufs.0[advance1..]), IoSliceMut::new(&mut bufs.1[advance2..])];
-        let bytes = self.inner.read_vectored(&mut bufs)?;
         g.len += bytes;
         drop(g);
 
+        self.read += bytes as u64;
+        // Only report progress when bytes were actually pulled; a post-EOF poll
+        // reads zero and must not re-fire the callback with an unchanged count.
+        if bytes > 0 {
+            let total = self.read;
+            if let Some(progress) = self.progress.as_mut() {
+                progress(total);
+            }
+        }
+
         Ok(bytes)
     }
+
+    /// Reads exactly `buf.len()` bytes into `buf`.
+    ///
+    /// The internal deque is drained first and then the inner reader is polled
+    /// via [`try_fill_buf`](Self::try_fill_buf) until the slice is full. Unlike
+    /// the [`Read::read_exact`] default this copies through the ring with at
+    /// most two `copy_from_slice` calls per refill rather than byte-by-byte. On
+    /// error any bytes already copied into `buf` are left in place.
+    ///
+    /// # Errors
+    /// Returns [`ErrorKind::UnexpectedEof`](io::ErrorKind::UnexpectedEof) if the
+    /// inner reader reaches end of file before `buf` is filled, or any I/O error
+    /// raised by the inner reader.
+    pub fn fill(&mut self, mut buf: &mut [u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            if self.buf.is_empty() && self.try_fill_buf()? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "failed to fill whole buffer",
+                ));
+            }
+
+            let copied = {
+                let (head, tail) = self.buf.as_slices();
+                let n1 = head.len().min(buf.len());
+                buf[..n1].copy_from_slice(&head[..n1]);
+                let n2 = tail.len().min(buf.len() - n1);
+                buf[n1..n1 + n2].copy_from_slice(&tail[..n2]);
+                n1 + n2
+            };
+
+            self.buf.drain(0..copied);
+            buf = &mut buf[copied..];
+        }
+
+        Ok(())
+    }
+
+    /// Hands the front contiguous slice of buffered data to `f` and advances
+    /// the head by the amount `f` reports it consumed.
+    ///
+    /// The [`ReaderPolicy`] is consulted first, just like [`fill_buf`], so the
+    /// two entry points stay consistent; `Ok(None)` is returned only when the
+    /// deque is still empty after the refill (EOF). A buffered hit touches the
+    /// ring with a single bounds check and no intermediate [`BufRead`] borrow,
+    /// which is what tight loops of tiny reads want.
+    ///
+    /// [`fill_buf`]: BufRead::fill_buf
+    ///
+    /// # Errors
+    /// Return an I/O error if it happens in the internal reader.
+    pub fn consume_with<F, T>(&mut self, f: F) -> io::Result<Option<T>>
+            where F: FnOnce(&[u8]) -> (usize, T) {
+        if self.policy.before_read(&self.buf).0 {
+            self.try_fill_buf()?;
+        }
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+
+        let (amt, value) = f(self.buf.as_slices().0);
+        self.buf.drain(0..amt);
+        self.policy.after_consume(&self.buf, amt);
+        Ok(Some(value))
+    }
+
+    /// Reads a single byte, returning `Ok(None)` on EOF.
+    ///
+    /// A thin wrapper over [`consume_with`](Self::consume_with) illustrating the
+    /// single-bounds-check fast path for the smallest possible read.
+    pub fn read_u8(&mut self) -> io::Result<Option<u8>> {
+        self.consume_with(|available| (1, available[0]))
+    }
 }
 
-impl<R: Read, A : Array<Item=u8>> Read for BufReader<R, A> {
+impl<R: Read, A : Array<Item=u8>, P: ReaderPolicy> Read for BufReader<R, A, P> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let mut ibuf = self.fill_buf()?;
-        let nread = ibuf.read(buf)?;
+        let nread = self.consume_with(|available| {
+            let n = available.len().min(buf.len());
+            buf[..n].copy_from_slice(&available[..n]);
+            (n, n)
+        })?;
+        Ok(nread.unwrap_or(0))
+    }
+
+    #[cfg(feature = "read_buf")]
+    fn read_buf(&mut self, mut cursor: io::BorrowedCursor<'_>) -> io::Result<()> {
+        // If there is nothing buffered and the caller's cursor has at least as
+        // much free space as our whole capacity, fill it directly and skip an extra
+        // copy through the ring.
+        if self.buf.is_empty() && cursor.capacity() >= self.buf.capacity() {
+            return self.inner.read_buf(cursor);
+        }
+
+        let prev = cursor.written();
+        let mut rem = self.fill_buf()?;
+        rem.read_buf(cursor.reborrow())?;
+        let nread = cursor.written() - prev;
         self.consume(nread);
-        Ok(nread)
+        Ok(())
+    }
+}
+
+impl<R: Seek, A : Array<Item=u8>, P: ReaderPolicy> BufReader<R, A, P> {
+    /// Seeks relative to the current logical position.
+    ///
+    /// A forward seek that lands inside the currently buffered bytes is
+    /// satisfied by advancing the head, reusing the buffer; any other target
+    /// discards the buffer and seeks the inner reader. Skipping forward within
+    /// the buffered window keeps the lookahead; a backward seek discards it.
+    ///
+    /// # Errors
+    /// Return an I/O error if it happens in the internal reader.
+    pub fn seek_relative(&mut self, offset: i64) -> io::Result<()> {
+        let buffered = self.buffer().len() as i64;
+        if offset >= 0 && offset <= buffered {
+            self.buf.drain(0..offset as usize);
+            self.policy.after_consume(&self.buf, offset as usize);
+            Ok(())
+        } else {
+            self.inner.seek(SeekFrom::Current(offset - buffered))?;
+            self.buffer_mut().clear();
+            Ok(())
+        }
     }
 }
 
-impl<R: Read, A : Array<Item=u8>> BufRead for BufReader<R, A> {
+impl<R: Seek, A : Array<Item=u8>, P: ReaderPolicy> Seek for BufReader<R, A, P> {
+    /// Seeks to an offset, in bytes, in the underlying reader.
+    ///
+    /// The logical position is the inner reader's position minus the number of
+    /// buffered-but-unconsumed bytes. A `SeekFrom::Current(n)` that lands
+    /// inside the buffered window advances the head without seeking the inner
+    /// reader, but still calls its `stream_position` to compute the returned
+    /// position; every other seek discards the buffer and seeks the inner
+    /// reader.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if let SeekFrom::Current(n) = pos {
+            let buffered = self.buffer().len() as i64;
+            if n >= 0 && n <= buffered {
+                self.buf.drain(0..n as usize);
+                self.policy.after_consume(&self.buf, n as usize);
+                return Ok(self.inner.stream_position()? - self.buffer().len() as u64);
+            }
+            let result = self.inner.seek(SeekFrom::Current(n - buffered))?;
+            self.buffer_mut().clear();
+            Ok(result)
+        } else {
+            let result = self.inner.seek(pos)?;
+            self.buffer_mut().clear();
+            Ok(result)
+        }
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.inner.stream_position()? - self.buffer().len() as u64)
+    }
+}
+
+impl<R: Read, A : Array<Item=u8>, P: ReaderPolicy> BufRead for BufReader<R, A, P> {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        if self.buf.is_empty() {
+        if self.policy.before_read(&self.buf).0 {
             self.try_fill_buf()?;
         }
 
@@ -127,5 +420,142 @@ impl<R: Read, A : Array<Item=u8>> BufRead for BufReader<R, A> {
 
     fn consume(&mut self, amt: usize) {
         self.buf.drain(0..amt);
+        self.policy.after_consume(&self.buf, amt);
+    }
+}
+
+/// `std::io::BufWriter` replacement driven by `ArrayDeque`.
+pub struct BufWriter<W: Write, A> where A : Array<Item=u8> {
+    inner: W,
+    buf: ArrayDeque<A, Saturating>,
+}
+
+impl<W: Write, A : Array<Item=u8>> BufWriter<W, A> {
+    /// Creates a new BufWriter<W, A>
+    pub fn new(inner: W) -> BufWriter<W, A> {
+        BufWriter { inner, buf: ArrayDeque::<A, Saturating>::new() }
+    }
+
+    /// Unwraps this `BufWriter<W, A>`, returning the underlying writer.
+    ///
+    /// The buffer is drained into the internal writer before it is returned.
+    /// If draining fails, the buffered bytes are lost together with the error.
+    pub fn into_inner(self) -> io::Result<W> {
+        let mut this = std::mem::ManuallyDrop::new(self);
+        let res = this.flush_buf();
+        // SAFETY: `this` is wrapped in `ManuallyDrop` and never dropped, so
+        // each field is moved out exactly once.
+        let inner = unsafe { std::ptr::read(&this.inner) };
+        let buf = unsafe { std::ptr::read(&this.buf) };
+        drop(buf);
+        res.map(|()| inner)
+    }
+}
+
+impl<W: Write, A : Array<Item=u8>> BufWriter<W, A> {
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns a reference to the internal deque.
+    pub fn buffer(&self) -> &ArrayDeque::<A, Saturating> {
+        &self.buf
+    }
+
+    /// Returns a mutable reference to the internal deque.
+    pub fn buffer_mut(&mut self) -> &mut ArrayDeque::<A, Saturating> {
+        &mut self.buf
+    }
+}
+
+impl<W: Write, A : Array<Item=u8>> BufWriter<W, A> {
+    /// Drains all buffered bytes into the internal writer.
+    ///
+    /// The deque is a ring, so both halves returned by `as_slices()` are
+    /// submitted through a single `write_vectored` call and the accepted
+    /// prefix is then removed from the head.
+    ///
+    /// # Errors
+    /// Return an I/O error if it happens in the internal writer.
+    fn flush_buf(&mut self) -> io::Result<()> {
+        while !self.buf.is_empty() {
+            let bytes = {
+                let (head, tail) = self.buf.as_slices();
+                let bufs = [IoSlice::new(head), IoSlice::new(tail)];
+                self.inner.write_vectored(&bufs)?
+            };
+            if bytes == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write the buffered data",
+                ));
+            }
+            self.buf.drain(0..bytes);
+        }
+
+        Ok(())
+    }
+
+    /// Copies `data` into the tail of the deque with at most two
+    /// `copy_from_slice` calls. The caller must ensure `data` fits in the
+    /// currently free space.
+    fn push_tail(&mut self, data: &[u8]) {
+        let empty_pos = self.buf.len();
+        unsafe {
+            self.buf.set_len(empty_pos + data.len());
+        }
+
+        let (first, second) = self.buf.as_mut_slices();
+        let n1 = if empty_pos < first.len() {
+            let n = (first.len() - empty_pos).min(data.len());
+            first[empty_pos..empty_pos + n].copy_from_slice(&data[..n]);
+            n
+        } else {
+            0
+        };
+
+        if n1 < data.len() {
+            let start = empty_pos + n1 - first.len();
+            let rest = &data[n1..];
+            second[start..start + rest.len()].copy_from_slice(rest);
+        }
+    }
+}
+
+impl<W: Write, A : Array<Item=u8>> Write for BufWriter<W, A> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A write at least as large as our capacity can't benefit from
+        // buffering: flush what we have and hand it straight to the inner
+        // writer. This also covers the degenerate zero-capacity array, which
+        // could never buffer a byte.
+        if buf.len() >= self.buf.capacity() {
+            self.flush_buf()?;
+            return self.inner.write(buf);
+        }
+
+        if buf.len() > self.buf.capacity() - self.buf.len() {
+            self.flush_buf()?;
+        }
+
+        // The deque now has room for all of `buf`; copy it into the tail.
+        self.push_tail(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write, A : Array<Item=u8>> Drop for BufWriter<W, A> {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
     }
 }